@@ -0,0 +1,195 @@
+use block_cipher_trait::BlockCipher;
+use block_cipher_trait::generic_array::typenum::Unsigned;
+use block_cipher_trait::generic_array::GenericArray;
+use block_padding::Padding;
+use traits::BlockMode;
+use utils::{xor, Block};
+use core::marker::PhantomData;
+use errors::{InvalidKeyIvLength, BlockModeError};
+use std::vec::Vec;
+
+/// Cipher feedback (CFB) block cipher mode instance.
+///
+/// Note that, like XTS, this mode needs no padding: the final partial block
+/// (if any) is encrypted with a keystream truncated to its length, so `pos`
+/// is ignored by `encrypt`/`decrypt`.
+pub struct Cfb<C: BlockCipher, P: Padding> {
+    cipher: C,
+    iv: Block<C>,
+    _p: PhantomData<P>,
+}
+
+impl<C: BlockCipher, P: Padding> BlockMode<C, P> for Cfb<C, P> {
+    fn new(cipher: C, iv: &Block<C>) -> Self {
+        Self { cipher, iv: iv.clone(), _p: Default::default() }
+    }
+
+    fn new_var(key: &[u8], iv: &[u8]) -> Result<Self, InvalidKeyIvLength> {
+        if key.len() != C::KeySize::to_usize() || iv.len() != C::BlockSize::to_usize() {
+            return Err(InvalidKeyIvLength)
+        }
+
+        let cipher = C::new_varkey(key).map_err(|_| InvalidKeyIvLength)?;
+        let mut block: Block<C> = Default::default();
+        block.copy_from_slice(iv);
+        Ok(Self::new(cipher, &block))
+    }
+
+    fn encrypt_blocks(&mut self, blocks: &mut [Block<C>]) {
+        for block in blocks {
+            self.cipher.encrypt_block(&mut self.iv);
+            xor(&mut self.iv, block);
+            block.copy_from_slice(&self.iv);
+        }
+    }
+
+    fn decrypt_blocks(&mut self, blocks: &mut [Block<C>]) {
+        for block in blocks {
+            let mut keystream = self.iv.clone();
+            self.cipher.encrypt_block(&mut keystream);
+            let next_iv = block.clone();
+            xor(block, &keystream);
+            self.iv = next_iv;
+        }
+    }
+
+    fn get_iv_state(&self) -> GenericArray<u8, C::BlockSize> {
+        self.iv.clone()
+    }
+
+    /// Encrypt message in-place.
+    ///
+    /// pos argument is ignored, since padding is not used with CFB.
+    fn encrypt(mut self, buffer: &mut [u8], _: usize) -> Result<&[u8], BlockModeError> {
+        let bs = C::BlockSize::to_usize();
+        for chunk in buffer.chunks_mut(bs) {
+            self.cipher.encrypt_block(&mut self.iv);
+            xor(chunk, &self.iv[..chunk.len()]);
+            self.iv[..chunk.len()].copy_from_slice(chunk);
+        }
+        Ok(buffer)
+    }
+
+    /// Decrypt message in-place.
+    fn decrypt(mut self, buffer: &mut [u8]) -> Result<&[u8], BlockModeError> {
+        let bs = C::BlockSize::to_usize();
+        for chunk in buffer.chunks_mut(bs) {
+            let mut keystream = self.iv.clone();
+            self.cipher.encrypt_block(&mut keystream);
+            self.iv[..chunk.len()].copy_from_slice(chunk);
+            xor(chunk, &keystream[..chunk.len()]);
+        }
+        Ok(buffer)
+    }
+
+    /// Encrypt message and store result in vector.
+    #[cfg(feature = "std")]
+    fn encrypt_vec(self, plaintext: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::from(plaintext);
+        match self.encrypt(&mut buf, 0) {
+            Ok(_) => buf,
+            _ => panic!()
+        }
+    }
+
+    /// Decrypt message and store result in vector.
+    #[cfg(feature = "std")]
+    fn decrypt_vec(self, ciphertext: &[u8]) -> Result<Vec<u8>, BlockModeError> {
+        let mut buf = Vec::from(ciphertext);
+        match self.decrypt(&mut buf) {
+            Ok(_) => Ok(buf),
+            Err(e) => Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use block_cipher_trait::InvalidKeyLength;
+    use block_cipher_trait::generic_array::typenum::U16;
+    use block_padding::ZeroPadding;
+
+    /// Minimal non-cryptographic stand-in `BlockCipher` used only to
+    /// exercise `Cfb`'s block handling: XOR the key into the block, then
+    /// rotate it by one byte (and invert in `decrypt_block`). This crate has
+    /// no real block cipher as a dependency to generate NIST vectors
+    /// against, so this test pins down this cipher's own known-answer
+    /// output instead.
+    #[derive(Clone)]
+    struct TestCipher {
+        key: GenericArray<u8, U16>,
+    }
+
+    impl BlockCipher for TestCipher {
+        type KeySize = U16;
+        type BlockSize = U16;
+        type ParBlocks = U16;
+
+        fn new(key: &GenericArray<u8, U16>) -> Self {
+            TestCipher { key: key.clone() }
+        }
+
+        fn new_varkey(key: &[u8]) -> Result<Self, InvalidKeyLength> {
+            if key.len() != 16 {
+                return Err(InvalidKeyLength)
+            }
+            let mut k: GenericArray<u8, U16> = Default::default();
+            k.copy_from_slice(key);
+            Ok(TestCipher { key: k })
+        }
+
+        fn encrypt_block(&self, block: &mut GenericArray<u8, U16>) {
+            xor(block, &self.key);
+            block.rotate_left(1);
+        }
+
+        fn decrypt_block(&self, block: &mut GenericArray<u8, U16>) {
+            block.rotate_right(1);
+            xor(block, &self.key);
+        }
+    }
+
+    fn key_and_iv() -> (GenericArray<u8, U16>, Block<TestCipher>) {
+        let mut key: GenericArray<u8, U16> = Default::default();
+        for (i, b) in key.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        (key, Default::default())
+    }
+
+    #[test]
+    fn roundtrip() {
+        let (key, iv) = key_and_iv();
+        // Spans a full block plus a partial one, to exercise the
+        // last-chunk keystream truncation.
+        let plaintext = b"0123456789abcdefXYZ".to_vec();
+
+        let mut buf = plaintext.clone();
+        let cipher = TestCipher::new(&key);
+        Cfb::<TestCipher, ZeroPadding>::new(cipher.clone(), &iv)
+            .encrypt(&mut buf, 0).unwrap();
+        assert_ne!(buf, plaintext);
+
+        Cfb::<TestCipher, ZeroPadding>::new(cipher, &iv)
+            .decrypt(&mut buf).unwrap();
+        assert_eq!(buf, plaintext);
+    }
+
+    #[test]
+    fn known_answer() {
+        let (key, iv) = key_and_iv();
+        let plaintext = b"0123456789abcdefXYZ".to_vec();
+        let mut buf = plaintext.clone();
+        Cfb::<TestCipher, ZeroPadding>::new(TestCipher::new(&key), &iv)
+            .encrypt(&mut buf, 0).unwrap();
+        assert_eq!(
+            buf,
+            vec![
+                0x31, 0x33, 0x31, 0x37, 0x31, 0x33, 0x31, 0x3f,
+                0x31, 0x33, 0x6a, 0x6e, 0x6e, 0x6a, 0x6a, 0x66,
+                0x6a, 0x6a, 0x6e,
+            ]
+        );
+    }
+}
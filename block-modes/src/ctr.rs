@@ -0,0 +1,240 @@
+use block_cipher_trait::BlockCipher;
+use block_cipher_trait::generic_array::typenum::Unsigned;
+use block_cipher_trait::generic_array::GenericArray;
+use block_padding::Padding;
+use traits::BlockMode;
+use utils::{xor, Block};
+use core::marker::PhantomData;
+use errors::{InvalidKeyIvLength, BlockModeError};
+use std::vec::Vec;
+
+/// CTR mode instance for 128 bit block ciphers.
+///
+/// The IV is treated as a big-endian `[u64; 2]` counter: it is incremented
+/// by one per block and XORed into the buffer after being run through the
+/// cipher. Unlike a naive block-at-a-time implementation, this mode
+/// exploits `C::ParBlocks` to generate several counter blocks at once and
+/// encrypt them with the cipher's parallel block encryption, which gives a
+/// large throughput win on backends (e.g. AES-NI) that implement it.
+pub struct Ctr128<C: BlockCipher, P: Padding> {
+    cipher: C,
+    counter: [u64; 2],
+    _p: PhantomData<P>,
+}
+
+impl<C: BlockCipher, P: Padding> Ctr128<C, P> {
+    fn counter_block(&self) -> Block<C> {
+        let mut block: Block<C> = Default::default();
+        block[..8].copy_from_slice(&self.counter[0].to_be_bytes());
+        block[8..16].copy_from_slice(&self.counter[1].to_be_bytes());
+        block
+    }
+
+    fn increment_counter(&mut self) {
+        let (low, carry) = self.counter[1].overflowing_add(1);
+        self.counter[1] = low;
+        if carry {
+            self.counter[0] = self.counter[0].wrapping_add(1);
+        }
+    }
+
+    /// XOR the keystream into an arbitrary-length buffer, generating
+    /// `C::ParBlocks` counter blocks at a time.
+    fn apply_keystream(&mut self, buffer: &mut [u8]) {
+        let bs = C::BlockSize::to_usize();
+        let par_blocks = C::ParBlocks::to_usize();
+        for chunk in buffer.chunks_mut(bs * par_blocks) {
+            let needed = (chunk.len() + bs - 1) / bs;
+            let mut keystream: GenericArray<Block<C>, C::ParBlocks> = Default::default();
+            for block in keystream.iter_mut().take(needed) {
+                *block = self.counter_block();
+                self.increment_counter();
+            }
+            self.cipher.encrypt_blocks(&mut keystream);
+            for (data, block) in chunk.chunks_mut(bs).zip(keystream.iter()) {
+                let n = data.len();
+                xor(data, &block[..n]);
+            }
+        }
+    }
+
+    /// XOR the keystream into full blocks, generating `C::ParBlocks`
+    /// counter blocks at a time.
+    fn process_blocks(&mut self, blocks: &mut [Block<C>]) {
+        let par_blocks = C::ParBlocks::to_usize();
+        for chunk in blocks.chunks_mut(par_blocks) {
+            let mut keystream: GenericArray<Block<C>, C::ParBlocks> = Default::default();
+            for block in keystream.iter_mut().take(chunk.len()) {
+                *block = self.counter_block();
+                self.increment_counter();
+            }
+            self.cipher.encrypt_blocks(&mut keystream);
+            for (block, ks) in chunk.iter_mut().zip(keystream.iter()) {
+                xor(block, ks);
+            }
+        }
+    }
+}
+
+impl<C: BlockCipher, P: Padding> BlockMode<C, P> for Ctr128<C, P> {
+    fn new(cipher: C, iv: &Block<C>) -> Self {
+        assert_eq!(C::BlockSize::to_usize(), 128 / 8); // Only block ciphers with 128 bit block size
+        let mut counter = [0u64; 2];
+        let mut high = [0u8; 8];
+        let mut low = [0u8; 8];
+        high.copy_from_slice(&iv[..8]);
+        low.copy_from_slice(&iv[8..16]);
+        counter[0] = u64::from_be_bytes(high);
+        counter[1] = u64::from_be_bytes(low);
+        Self { cipher, counter, _p: Default::default() }
+    }
+
+    fn new_var(key: &[u8], iv: &[u8]) -> Result<Self, InvalidKeyIvLength> {
+        assert_eq!(C::BlockSize::to_usize(), 128 / 8); // Only block ciphers with 128 bit block size
+        if key.len() != C::KeySize::to_usize() || iv.len() != C::BlockSize::to_usize() {
+            return Err(InvalidKeyIvLength)
+        }
+
+        let cipher = C::new_varkey(key).map_err(|_| InvalidKeyIvLength)?;
+        let mut block: Block<C> = Default::default();
+        block[..].copy_from_slice(iv);
+        Ok(Self::new(cipher, &block))
+    }
+
+    fn encrypt_blocks(&mut self, blocks: &mut [Block<C>]) {
+        self.process_blocks(blocks);
+    }
+
+    fn decrypt_blocks(&mut self, blocks: &mut [Block<C>]) {
+        self.process_blocks(blocks);
+    }
+
+    fn get_iv_state(&self) -> GenericArray<u8, C::BlockSize> {
+        self.counter_block()
+    }
+
+    /// Encrypt message in-place.
+    ///
+    /// pos argument is ignored, since CTR mode needs no padding.
+    fn encrypt(mut self, buffer: &mut [u8], _: usize) -> Result<&[u8], BlockModeError> {
+        self.apply_keystream(buffer);
+        Ok(buffer)
+    }
+
+    /// Decrypt message in-place.
+    fn decrypt(mut self, buffer: &mut [u8]) -> Result<&[u8], BlockModeError> {
+        self.apply_keystream(buffer);
+        Ok(buffer)
+    }
+
+    /// Encrypt message and store result in vector.
+    #[cfg(feature = "std")]
+    fn encrypt_vec(self, plaintext: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::from(plaintext);
+        match self.encrypt(&mut buf, 0) {
+            Ok(_) => buf,
+            _ => panic!()
+        }
+    }
+
+    /// Decrypt message and store result in vector.
+    #[cfg(feature = "std")]
+    fn decrypt_vec(self, ciphertext: &[u8]) -> Result<Vec<u8>, BlockModeError> {
+        let mut buf = Vec::from(ciphertext);
+        match self.decrypt(&mut buf) {
+            Ok(_) => Ok(buf),
+            Err(e) => Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use block_cipher_trait::InvalidKeyLength;
+    use block_cipher_trait::generic_array::typenum::{U4, U16};
+    use block_padding::ZeroPadding;
+
+    /// Minimal non-cryptographic stand-in `BlockCipher` used only to
+    /// exercise `Ctr128`'s block handling: XOR the key into the block, then
+    /// rotate it by one byte (and invert in `decrypt_block`). This crate has
+    /// no real block cipher as a dependency to generate NIST vectors
+    /// against, so this test pins down this cipher's own known-answer
+    /// output instead.
+    #[derive(Clone)]
+    struct TestCipher {
+        key: GenericArray<u8, U16>,
+    }
+
+    impl BlockCipher for TestCipher {
+        type KeySize = U16;
+        type BlockSize = U16;
+        type ParBlocks = U4;
+
+        fn new(key: &GenericArray<u8, U16>) -> Self {
+            TestCipher { key: key.clone() }
+        }
+
+        fn new_varkey(key: &[u8]) -> Result<Self, InvalidKeyLength> {
+            if key.len() != 16 {
+                return Err(InvalidKeyLength)
+            }
+            let mut k: GenericArray<u8, U16> = Default::default();
+            k.copy_from_slice(key);
+            Ok(TestCipher { key: k })
+        }
+
+        fn encrypt_block(&self, block: &mut GenericArray<u8, U16>) {
+            xor(block, &self.key);
+            block.rotate_left(1);
+        }
+
+        fn decrypt_block(&self, block: &mut GenericArray<u8, U16>) {
+            block.rotate_right(1);
+            xor(block, &self.key);
+        }
+    }
+
+    fn key_and_iv() -> (GenericArray<u8, U16>, Block<TestCipher>) {
+        let mut key: GenericArray<u8, U16> = Default::default();
+        for (i, b) in key.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        (key, Default::default())
+    }
+
+    #[test]
+    fn roundtrip() {
+        let (key, iv) = key_and_iv();
+        // Spans a full block plus a partial one, to exercise the
+        // last-chunk keystream truncation.
+        let plaintext = b"0123456789abcdefXYZ".to_vec();
+
+        let mut buf = plaintext.clone();
+        let cipher = TestCipher::new(&key);
+        Ctr128::<TestCipher, ZeroPadding>::new(cipher.clone(), &iv)
+            .encrypt(&mut buf, 0).unwrap();
+        assert_ne!(buf, plaintext);
+
+        Ctr128::<TestCipher, ZeroPadding>::new(cipher, &iv)
+            .decrypt(&mut buf).unwrap();
+        assert_eq!(buf, plaintext);
+    }
+
+    #[test]
+    fn known_answer() {
+        let (key, iv) = key_and_iv();
+        let plaintext = b"0123456789abcdefXYZ".to_vec();
+        let mut buf = plaintext.clone();
+        Ctr128::<TestCipher, ZeroPadding>::new(TestCipher::new(&key), &iv)
+            .encrypt(&mut buf, 0).unwrap();
+        assert_eq!(
+            buf,
+            vec![
+                0x31, 0x33, 0x31, 0x37, 0x31, 0x33, 0x31, 0x3f,
+                0x31, 0x33, 0x6a, 0x6e, 0x6e, 0x6a, 0x6a, 0x66,
+                0x59, 0x5b, 0x59,
+            ]
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use block_cipher_trait::BlockCipher;
+use block_cipher_trait::generic_array::typenum::Unsigned;
+use utils::{xor, Block};
+use errors::{InvalidKeyIvLength, BlockModeError};
+
+/// Length-doubling tweakable (LDT) mode for messages between one and two
+/// block cipher blocks long.
+///
+/// XTS's ciphertext stealing only diffuses the final partial block
+/// locally, so a single flipped ciphertext bit in a short record only
+/// scrambles that last block. `Ldt` instead runs an encrypt-mix-encrypt,
+/// two-round Feistel-like construction over a tweakable block cipher built
+/// from `C` (the same xor-encrypt-xor primitive XTS uses, applied with two
+/// independent tweaks). A single flipped bit in the first (full) ciphertext
+/// block scrambles the whole plaintext; a single flipped bit in the trailing
+/// ciphertext fragment scrambles the first plaintext block and leaves the
+/// trailing plaintext fragment unchanged.
+///
+/// [1]: https://eprint.iacr.org/2017/841
+pub struct Ldt<C: BlockCipher> {
+    cipher: C,
+    tweak1: Block<C>,
+    tweak2: Block<C>,
+}
+
+impl<C: BlockCipher> Ldt<C> {
+    /// Construct an instance from an already-initialized cipher and two
+    /// already-derived tweaks.
+    pub fn new(cipher: C, tweak1: Block<C>, tweak2: Block<C>) -> Self {
+        assert_eq!(C::BlockSize::to_usize(), 128 / 8); // Only block ciphers with 128 bit block size
+        Self { cipher, tweak1, tweak2 }
+    }
+
+    /// Construct an instance the way `Xts` does: `key` is split into a
+    /// cipher key followed by two tweak keys, and `iv1`/`iv2` are each
+    /// encrypted with their respective tweak key to derive the two tweaks.
+    pub fn new_var(key: &[u8], iv1: &[u8], iv2: &[u8]) -> Result<Self, InvalidKeyIvLength> {
+        assert_eq!(C::BlockSize::to_usize(), 128 / 8); // Only block ciphers with 128 bit block size
+        let ks = C::KeySize::to_usize();
+        let bs = C::BlockSize::to_usize();
+        if key.len() != ks * 3 || iv1.len() != bs || iv2.len() != bs {
+            return Err(InvalidKeyIvLength)
+        }
+
+        let cipher = C::new_varkey(&key[..ks]).map_err(|_| InvalidKeyIvLength)?;
+        let tweak_cipher1 = C::new_varkey(&key[ks..2 * ks]).map_err(|_| InvalidKeyIvLength)?;
+        let tweak_cipher2 = C::new_varkey(&key[2 * ks..3 * ks]).map_err(|_| InvalidKeyIvLength)?;
+
+        let mut tweak1: Block<C> = Default::default();
+        tweak1.copy_from_slice(iv1);
+        tweak_cipher1.encrypt_block(&mut tweak1);
+
+        let mut tweak2: Block<C> = Default::default();
+        tweak2.copy_from_slice(iv2);
+        tweak_cipher2.encrypt_block(&mut tweak2);
+
+        Ok(Self { cipher, tweak1, tweak2 })
+    }
+
+    fn tweak_encrypt(&self, tweak: &Block<C>, block: &mut Block<C>) {
+        xor(block, tweak);
+        self.cipher.encrypt_block(block);
+        xor(block, tweak);
+    }
+
+    fn tweak_decrypt(&self, tweak: &Block<C>, block: &mut Block<C>) {
+        xor(block, tweak);
+        self.cipher.decrypt_block(block);
+        xor(block, tweak);
+    }
+
+    /// Encrypt a buffer of length `n` with `bs <= n < 2 * bs` in-place.
+    pub fn encrypt(&self, buffer: &mut [u8]) -> Result<(), BlockModeError> {
+        let bs = C::BlockSize::to_usize();
+        let n = buffer.len();
+        if n < bs || n >= 2 * bs {
+            return Err(BlockModeError)
+        }
+        let r = n - bs;
+
+        // (1) zero-pad R to R*
+        let mut r_star: Block<C> = Default::default();
+        r_star[..r].copy_from_slice(&buffer[bs..]);
+
+        // (2) U = E_tweak1(L XOR R*)
+        let mut u: Block<C> = Default::default();
+        u.copy_from_slice(&buffer[..bs]);
+        xor(&mut u, &r_star);
+        self.tweak_encrypt(&self.tweak1, &mut u);
+
+        // (3) R' = R XOR (first r bytes of U)
+        for (byte, u_byte) in buffer[bs..].iter_mut().zip(u.iter()) {
+            *byte ^= u_byte;
+        }
+
+        // (4) zero-pad R' to R'*
+        let mut r_prime_star: Block<C> = Default::default();
+        r_prime_star[..r].copy_from_slice(&buffer[bs..]);
+
+        // (5) L' = E_tweak2(U XOR R'*)
+        let mut l_prime = u;
+        xor(&mut l_prime, &r_prime_star);
+        self.tweak_encrypt(&self.tweak2, &mut l_prime);
+
+        buffer[..bs].copy_from_slice(&l_prime);
+        Ok(())
+    }
+
+    /// Decrypt a buffer of length `n` with `bs <= n < 2 * bs` in-place.
+    pub fn decrypt(&self, buffer: &mut [u8]) -> Result<(), BlockModeError> {
+        let bs = C::BlockSize::to_usize();
+        let n = buffer.len();
+        if n < bs || n >= 2 * bs {
+            return Err(BlockModeError)
+        }
+        let r = n - bs;
+
+        // Invert (4)-(5): U = D_tweak2(L') XOR R'*
+        let mut r_prime_star: Block<C> = Default::default();
+        r_prime_star[..r].copy_from_slice(&buffer[bs..]);
+
+        let mut u: Block<C> = Default::default();
+        u.copy_from_slice(&buffer[..bs]);
+        self.tweak_decrypt(&self.tweak2, &mut u);
+        xor(&mut u, &r_prime_star);
+
+        // Invert (3): R = R' XOR (first r bytes of U)
+        for (byte, u_byte) in buffer[bs..].iter_mut().zip(u.iter()) {
+            *byte ^= u_byte;
+        }
+
+        // Invert (2): L = D_tweak1(U) XOR R*
+        let mut r_star: Block<C> = Default::default();
+        r_star[..r].copy_from_slice(&buffer[bs..]);
+
+        let mut l = u;
+        self.tweak_decrypt(&self.tweak1, &mut l);
+        xor(&mut l, &r_star);
+
+        buffer[..bs].copy_from_slice(&l);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use block_cipher_trait::generic_array::GenericArray;
+    use block_cipher_trait::InvalidKeyLength;
+    use block_cipher_trait::generic_array::typenum::U16;
+
+    /// Minimal non-cryptographic stand-in `BlockCipher` used only to
+    /// exercise `Ldt`'s block handling: XOR the key into the block, then
+    /// rotate it by one byte (and invert in `decrypt_block`). This crate has
+    /// no real block cipher as a dependency to generate NIST vectors
+    /// against, so these tests check invertibility directly instead.
+    #[derive(Clone)]
+    struct TestCipher {
+        key: GenericArray<u8, U16>,
+    }
+
+    impl BlockCipher for TestCipher {
+        type KeySize = U16;
+        type BlockSize = U16;
+        type ParBlocks = U16;
+
+        fn new(key: &GenericArray<u8, U16>) -> Self {
+            TestCipher { key: key.clone() }
+        }
+
+        fn new_varkey(key: &[u8]) -> Result<Self, InvalidKeyLength> {
+            if key.len() != 16 {
+                return Err(InvalidKeyLength)
+            }
+            let mut k: GenericArray<u8, U16> = Default::default();
+            k.copy_from_slice(key);
+            Ok(TestCipher { key: k })
+        }
+
+        fn encrypt_block(&self, block: &mut GenericArray<u8, U16>) {
+            xor(block, &self.key);
+            block.rotate_left(1);
+        }
+
+        fn decrypt_block(&self, block: &mut GenericArray<u8, U16>) {
+            block.rotate_right(1);
+            xor(block, &self.key);
+        }
+    }
+
+    fn ldt() -> Ldt<TestCipher> {
+        let mut key: GenericArray<u8, U16> = Default::default();
+        for (i, b) in key.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let tweak1: Block<TestCipher> = Default::default();
+        let mut tweak2: Block<TestCipher> = Default::default();
+        tweak2[0] = 1;
+        Ldt::new(TestCipher::new(&key), tweak1, tweak2)
+    }
+
+    /// `r == 1`: the right fragment is a single byte.
+    #[test]
+    fn roundtrip_short_tail() {
+        let mode = ldt();
+        let plaintext = b"0123456789abcdefX".to_vec(); // bs + 1 bytes
+        let mut buf = plaintext.clone();
+        mode.encrypt(&mut buf).unwrap();
+        assert_ne!(buf, plaintext);
+        mode.decrypt(&mut buf).unwrap();
+        assert_eq!(buf, plaintext);
+    }
+
+    /// `r == bs - 1`: the right fragment is almost a whole block.
+    #[test]
+    fn roundtrip_long_tail() {
+        let mode = ldt();
+        let plaintext = b"0123456789abcdefXYZ012345678901".to_vec(); // 2*bs - 1 bytes
+        let mut buf = plaintext.clone();
+        mode.encrypt(&mut buf).unwrap();
+        assert_ne!(buf, plaintext);
+        mode.decrypt(&mut buf).unwrap();
+        assert_eq!(buf, plaintext);
+    }
+
+    #[test]
+    fn rejects_out_of_range_lengths() {
+        let mode = ldt();
+        assert!(mode.encrypt(&mut [0u8; 15]).is_err());
+        assert!(mode.encrypt(&mut [0u8; 32]).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use block_cipher_trait::BlockCipher;
+use block_cipher_trait::generic_array::GenericArray;
+use block_padding::Padding;
+use utils::Block;
+use errors::{InvalidKeyIvLength, BlockModeError};
+use std::vec::Vec;
+
+/// Trait implemented by block cipher modes (CBC, CFB, CTR, XTS, ...).
+pub trait BlockMode<C: BlockCipher, P: Padding>: Sized {
+    /// Create a new mode instance from an already-initialized block cipher
+    /// and IV.
+    fn new(cipher: C, iv: &Block<C>) -> Self;
+
+    /// Create a new mode instance from raw key and IV bytes.
+    fn new_var(key: &[u8], iv: &[u8]) -> Result<Self, InvalidKeyIvLength>;
+
+    /// Encrypt a slice of full-sized blocks in-place.
+    fn encrypt_blocks(&mut self, blocks: &mut [Block<C>]);
+
+    /// Decrypt a slice of full-sized blocks in-place.
+    fn decrypt_blocks(&mut self, blocks: &mut [Block<C>]);
+
+    /// Encrypt message in-place.
+    fn encrypt(self, buffer: &mut [u8], pos: usize) -> Result<&[u8], BlockModeError>;
+
+    /// Decrypt message in-place.
+    fn decrypt(self, buffer: &mut [u8]) -> Result<&[u8], BlockModeError>;
+
+    /// Encrypt message and store result in vector.
+    #[cfg(feature = "std")]
+    fn encrypt_vec(self, plaintext: &[u8]) -> Vec<u8>;
+
+    /// Decrypt message and store result in vector.
+    #[cfg(feature = "std")]
+    fn decrypt_vec(self, ciphertext: &[u8]) -> Result<Vec<u8>, BlockModeError>;
+
+    /// Create a mode instance from an already-initialized block cipher and
+    /// IV, the same way [`new`](Self::new) does.
+    ///
+    /// This is a more explicit spelling of `new` for call sites that want to
+    /// make clear they are reusing an already keyed cipher instance across
+    /// many messages, rather than keying a fresh one.
+    fn new_from_blockcipher(cipher: C, iv: &Block<C>) -> Self {
+        Self::new(cipher, iv)
+    }
+
+    /// Create a mode instance from a fixed-size key, keying the cipher via
+    /// `C::new` instead of the length-checked, slice-based `C::new_varkey`
+    /// used by [`new_var`](Self::new_var).
+    fn new_fixkey(key: &GenericArray<u8, C::KeySize>, iv: &Block<C>) -> Self {
+        Self::new(C::new(key), iv)
+    }
+
+    /// Return the mode's current running state (e.g. the rolling tweak or
+    /// feedback register) so it can seed a later instance that continues
+    /// processing a subsequent chunk of the same stream.
+    fn get_iv_state(&self) -> GenericArray<u8, C::BlockSize>;
+}
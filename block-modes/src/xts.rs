@@ -9,19 +9,91 @@ use std::clone::Clone;
 use errors::{InvalidKeyIvLength, BlockModeError};
 use std::vec::Vec;
 
+/// Strategy for rolling the XTS tweak from one block to the next, and for
+/// deriving its initial value from a sector/block index.
+///
+/// `encrypt_blocks`/`decrypt_blocks` used to hardcode the IEEE P1619
+/// GF(2^128) multiply-by-alpha convention. This trait pulls that convention
+/// out so `Xts` can be used against on-disk formats that roll the tweak
+/// differently (e.g. big-endian byte order).
+pub trait Tweak<C: BlockCipher> {
+    /// Derive the initial tweak value for sector/block index `index`,
+    /// before it is encrypted with the tweak key.
+    fn first(index: u128) -> GenericArray<u8, C::BlockSize>;
+
+    /// Advance `tweak` in place to the value used by the next block.
+    fn next(tweak: &mut GenericArray<u8, C::BlockSize>);
+}
+
+/// The tweak convention from IEEE P1619: the tweak is treated as a
+/// little-endian 128-bit integer and rolled forward by multiplying by alpha
+/// in GF(2^128).
+pub struct Ieee1619Tweak;
+
+impl<C: BlockCipher> Tweak<C> for Ieee1619Tweak {
+    fn first(index: u128) -> GenericArray<u8, C::BlockSize> {
+        let mut tweak: GenericArray<u8, C::BlockSize> = Default::default();
+        tweak[..16].copy_from_slice(&index.to_le_bytes());
+        tweak
+    }
+
+    fn next(tweak: &mut GenericArray<u8, C::BlockSize>) {
+        get_next_tweak(tweak);
+    }
+}
+
+/// A big-endian tweak convention compatible with on-disk formats (e.g. some
+/// FreeBSD GELI-style layouts) that roll the tweak as a big-endian 128-bit
+/// integer instead of IEEE P1619's little-endian convention.
+pub struct Be1619Tweak;
+
+impl<C: BlockCipher> Tweak<C> for Be1619Tweak {
+    fn first(index: u128) -> GenericArray<u8, C::BlockSize> {
+        let mut tweak: GenericArray<u8, C::BlockSize> = Default::default();
+        let len = tweak.len();
+        tweak[len - 16..].copy_from_slice(&index.to_be_bytes());
+        tweak
+    }
+
+    fn next(tweak: &mut GenericArray<u8, C::BlockSize>) {
+        let mut carry = 0u8;
+        for byte in tweak.iter_mut().rev() {
+            let new_carry = (*byte >> 7) & 1;
+            *byte = (*byte << 1) | carry;
+            carry = new_carry;
+        }
+        if carry == 1 {
+            let len = tweak.len();
+            tweak[len - 1] ^= 0x87;
+        }
+    }
+}
+
 /// Xor encrypt xor with ciphertext stealing (XTS) block cipher mode instance.
 ///
 /// Note that `new` method ignores IV, so during initialization you can
 /// just pass `Default::default()` instead.
 ///
+/// The `T` type parameter selects the tweak-derivation strategy (see
+/// [`Tweak`]) and defaults to the IEEE P1619 convention.
+///
+/// To encrypt a long stream in independent chunks, call
+/// [`get_iv_state`](BlockMode::get_iv_state) after encrypting one chunk and
+/// pass the result as the `iv` of [`new_fixkey`](BlockMode::new_fixkey) or
+/// [`new_from_blockcipher`](BlockMode::new_from_blockcipher) for the next
+/// one, so the tweak keeps rolling forward instead of being derived again
+/// with the tweak key. `new_from_blockcipher` takes an already keyed cipher;
+/// `new_fixkey` runs the cipher's key schedule again via `C::new`.
+///
 /// [1]: https://en.wikipedia.org/wiki/Block_cipher_mode_of_operation#XTS
-pub struct Xts<C: BlockCipher, P: Padding> {
+pub struct Xts<C: BlockCipher, P: Padding, T: Tweak<C> = Ieee1619Tweak> {
     cipher: C,
     tweak: GenericArray<u8, C::BlockSize>,
     _p: PhantomData<P>,
+    _t: PhantomData<T>,
 }
 
-impl<C: BlockCipher, P: Padding> BlockMode<C, P> for Xts<C, P> {
+impl<C: BlockCipher, P: Padding, T: Tweak<C>> BlockMode<C, P> for Xts<C, P, T> {
     // If new is used to create the cipher, _iv already needs to be encrypted
     // by the second key so it can be used as a tweak value
     fn new(cipher: C, _iv: &Block<C>) -> Self {
@@ -29,7 +101,8 @@ impl<C: BlockCipher, P: Padding> BlockMode<C, P> for Xts<C, P> {
         Self {
             cipher,
             tweak: _iv.clone(),
-            _p: Default::default()
+            _p: Default::default(),
+            _t: Default::default()
         }
     }
 
@@ -49,7 +122,8 @@ impl<C: BlockCipher, P: Padding> BlockMode<C, P> for Xts<C, P> {
             Self {
             cipher,
             tweak,
-            _p: Default::default()
+            _p: Default::default(),
+            _t: Default::default()
             }
         )
     }
@@ -59,7 +133,7 @@ impl<C: BlockCipher, P: Padding> BlockMode<C, P> for Xts<C, P> {
             xor(block, &self.tweak);
             self.cipher.encrypt_block(block);
             xor(block, &self.tweak);
-            get_next_tweak(&mut self.tweak);
+            T::next(&mut self.tweak);
         }
     }
 
@@ -68,10 +142,14 @@ impl<C: BlockCipher, P: Padding> BlockMode<C, P> for Xts<C, P> {
             xor(block, &self.tweak);
             self.cipher.decrypt_block(block);
             xor(block, &self.tweak);
-            get_next_tweak(&mut self.tweak);
+            T::next(&mut self.tweak);
         }
     }
 
+    fn get_iv_state(&self) -> GenericArray<u8, C::BlockSize> {
+        self.tweak.clone()
+    }
+
     /// Encrypt message in-place.
     ///
     /// pos argument is ignored, since padding is not used with XTS.
@@ -105,7 +183,7 @@ impl<C: BlockCipher, P: Padding> BlockMode<C, P> for Xts<C, P> {
 
         if buffer_length % bs != 0 {
             let second_to_last_tweak = self.tweak.clone();
-            get_next_tweak(&mut self.tweak);
+            T::next(&mut self.tweak);
             let leftover = buffer_length - (buffer_length / bs) * bs;
 
             {
@@ -146,3 +224,80 @@ impl<C: BlockCipher, P: Padding> BlockMode<C, P> for Xts<C, P> {
         }
     }
 }
+
+impl<C: BlockCipher + Clone, P: Padding, T: Tweak<C>> Xts<C, P, T> {
+    /// Derive the tweak for sector/block index `index` the same way
+    /// `new_var` derives the initial tweak from an IV: pack `index` using
+    /// this instance's [`Tweak`] strategy, then encrypt it with
+    /// `tweak_cipher`.
+    pub fn sector_tweak(tweak_cipher: &C, index: u128) -> GenericArray<u8, C::BlockSize> {
+        let mut tweak = T::first(index);
+        tweak_cipher.encrypt_block(&mut tweak);
+        tweak
+    }
+
+    /// Encrypt a whole sector-addressed area (e.g. a disk or volume image)
+    /// in-place.
+    ///
+    /// `buffer` is split into chunks of `sector_size` bytes (the final chunk
+    /// may be shorter, but must still hold at least one full block); each
+    /// chunk is encrypted independently, with its own tweak derived by
+    /// `get_tweak` from its absolute sector index (`first_sector_index` plus
+    /// the chunk's position within `buffer`). The per-sector encryption
+    /// reuses the same ciphertext-stealing logic as [`BlockMode::encrypt`],
+    /// which requires a whole block to steal from; a trailing chunk shorter
+    /// than one block returns `BlockModeError` instead of panicking.
+    pub fn encrypt_area(
+        &self,
+        buffer: &mut [u8],
+        sector_size: usize,
+        first_sector_index: u128,
+        get_tweak: impl Fn(u128) -> GenericArray<u8, C::BlockSize>,
+    ) -> Result<(), BlockModeError> {
+        let bs = C::BlockSize::to_usize();
+        for (i, sector) in buffer.chunks_mut(sector_size).enumerate() {
+            if sector.len() < bs {
+                return Err(BlockModeError)
+            }
+            let index = first_sector_index + i as u128;
+            let instance = Self {
+                cipher: self.cipher.clone(),
+                tweak: get_tweak(index),
+                _p: PhantomData,
+                _t: PhantomData,
+            };
+            instance.encrypt(sector, 0)?;
+        }
+        Ok(())
+    }
+
+    /// Decrypt a whole sector-addressed area previously encrypted with
+    /// [`encrypt_area`](Self::encrypt_area), in-place.
+    ///
+    /// `get_tweak` must derive the same per-sector tweak used to encrypt it.
+    /// As in `encrypt_area`, a trailing chunk shorter than one block returns
+    /// `BlockModeError` instead of panicking.
+    pub fn decrypt_area(
+        &self,
+        buffer: &mut [u8],
+        sector_size: usize,
+        first_sector_index: u128,
+        get_tweak: impl Fn(u128) -> GenericArray<u8, C::BlockSize>,
+    ) -> Result<(), BlockModeError> {
+        let bs = C::BlockSize::to_usize();
+        for (i, sector) in buffer.chunks_mut(sector_size).enumerate() {
+            if sector.len() < bs {
+                return Err(BlockModeError)
+            }
+            let index = first_sector_index + i as u128;
+            let instance = Self {
+                cipher: self.cipher.clone(),
+                tweak: get_tweak(index),
+                _p: PhantomData,
+                _t: PhantomData,
+            };
+            instance.decrypt(sector)?;
+        }
+        Ok(())
+    }
+}